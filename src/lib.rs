@@ -8,7 +8,7 @@ pub mod components;
 use std::collections::HashMap;
 
 /// Entity identifier used to acces an Entity in the world.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, Hash, PartialEq)]
 pub struct Entity {
     pub idx: usize,
     pub uuid: usize,
@@ -1,11 +1,571 @@
 use anymap::AnyMap;
-use std::any::Any;
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell, UnsafeCell};
 use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
 
-use entity::Entity;
+use Entity;
 use EntityIterator;
+use components::Children;
+
+/// Wraps a stored component together with a borrow-tracking flag, the same
+/// way `std::cell::RefCell` does, so that a query can hand out a `&mut A`
+/// and a `&B` for different component types at the same time without
+/// needing to borrow the whole `World` mutably.
+///
+/// The flag is positive for every live shared borrow and `-1` for a live
+/// mutable borrow; acquiring a conflicting borrow panics.
+struct ComponentCell<T> {
+    value: UnsafeCell<T>,
+    borrow: Cell<isize>,
+}
+
+impl<T> ComponentCell<T> {
+    fn new(value: T) -> ComponentCell<T> {
+        ComponentCell {
+            value: UnsafeCell::new(value),
+            borrow: Cell::new(0),
+        }
+    }
+
+    fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    /// Bypasses the borrow flag entirely. Only safe to call while the
+    /// `World` itself is borrowed mutably, since that rules out any other
+    /// live borrow of this cell.
+    fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    fn borrow(&self) -> ComponentRef<T> {
+        let borrow = self.borrow.get();
+        if borrow < 0 {
+            panic!("component already mutably borrowed");
+        }
+        self.borrow.set(borrow + 1);
+        ComponentRef { cell: self }
+    }
+
+    fn borrow_mut(&self) -> ComponentRefMut<T> {
+        if self.borrow.get() != 0 {
+            panic!("component already borrowed");
+        }
+        self.borrow.set(-1);
+        ComponentRefMut { cell: self }
+    }
+}
+
+/// RAII guard for a shared borrow of a component, handed out by
+/// `World::get_component` and `World::query`/`query_mut`.
+pub struct ComponentRef<'a, T: 'a> {
+    cell: &'a ComponentCell<T>,
+}
+
+impl<'a, T> Deref for ComponentRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<'a, T> Drop for ComponentRef<'a, T> {
+    fn drop(&mut self) {
+        self.cell.borrow.set(self.cell.borrow.get() - 1);
+    }
+}
+
+/// RAII guard for a mutable borrow of a component, handed out by
+/// `World::query_mut`.
+pub struct ComponentRefMut<'a, T: 'a> {
+    cell: &'a ComponentCell<T>,
+}
+
+impl<'a, T> Deref for ComponentRefMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<'a, T> DerefMut for ComponentRefMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.cell.value.get() }
+    }
+}
+
+impl<'a, T> Drop for ComponentRefMut<'a, T> {
+    fn drop(&mut self) {
+        self.cell.borrow.set(0);
+    }
+}
+
+/// Implemented for tuples of component types that can be queried together
+/// with `World::query`.
+///
+/// Each implementation checks whether an entity carries every component in
+/// the tuple, and fetches references to them once a match is found.
+pub trait Join<'a> {
+    /// The tuple of component references yielded for a matching entity.
+    type Item;
+
+    /// Returns the OR of every component type's bit in the tuple, so
+    /// `World::query` can select only the archetypes that carry all of them.
+    fn mask(world: &World) -> u64;
+
+    /// Fetches the components for `entity`.
+    /// Only call this for an entity whose archetype mask is a superset of
+    /// `Self::mask`.
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item;
+}
+
+impl<'a, A: Any> Join<'a> for (A,) {
+    type Item = (ComponentRef<'a, A>,);
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        (
+            world.component_cell::<A>(entity)
+                .expect("Join::fetch called on a non-matching entity")
+                .borrow(),
+        )
+    }
+}
+
+impl<'a, A: Any, B: Any> Join<'a> for (A, B) {
+    type Item = (ComponentRef<'a, A>, ComponentRef<'a, B>);
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>() | world.component_bit::<B>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        (
+            world.component_cell::<A>(entity)
+                .expect("Join::fetch called on a non-matching entity").borrow(),
+            world.component_cell::<B>(entity)
+                .expect("Join::fetch called on a non-matching entity").borrow(),
+        )
+    }
+}
+
+impl<'a, A: Any, B: Any, C: Any> Join<'a> for (A, B, C) {
+    type Item = (ComponentRef<'a, A>, ComponentRef<'a, B>, ComponentRef<'a, C>);
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>() | world.component_bit::<B>() | world.component_bit::<C>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        (
+            world.component_cell::<A>(entity)
+                .expect("Join::fetch called on a non-matching entity").borrow(),
+            world.component_cell::<B>(entity)
+                .expect("Join::fetch called on a non-matching entity").borrow(),
+            world.component_cell::<C>(entity)
+                .expect("Join::fetch called on a non-matching entity").borrow(),
+        )
+    }
+}
+
+/// Iterates over the entities matching a `Join` tuple `J`, together with
+/// references to those components.
+///
+/// Only visits archetypes whose mask is a superset of `J::mask`.
+pub struct QueryIter<'a, J> {
+    world: &'a World,
+    archetypes: Vec<&'a Archetype>,
+    arch_idx: usize,
+    row: usize,
+    _join: ::std::marker::PhantomData<J>,
+}
+
+impl<'a, J: Join<'a>> Iterator for QueryIter<'a, J> {
+    type Item = (Entity, J::Item);
+
+    fn next(&mut self) -> Option<(Entity, J::Item)> {
+        loop {
+            let arch = match self.archetypes.get(self.arch_idx) {
+                Some(&arch) => arch,
+                None => return None,
+            };
+            if self.row >= arch.entities.len() {
+                self.arch_idx += 1;
+                self.row = 0;
+                continue;
+            }
+            let entity = arch.entities[self.row];
+            self.row += 1;
+            if self.world.ent_added.contains_key(&entity.idx) {
+                continue;
+            }
+            return Some((entity, J::fetch(self.world, &entity)));
+        }
+    }
+}
+
+/// Implemented for tuples of `&A` / `&mut A` component references that can
+/// be queried together with `World::query_mut`, handing out mutable and
+/// shared borrows of distinct component types in the same pass.
+pub trait JoinMut<'a> {
+    /// The component guards yielded for a matching entity: a bare guard for
+    /// a one-member tuple, a tuple of guards otherwise.
+    type Item;
+
+    /// Returns the OR of every component type's bit in the tuple, so
+    /// `World::query_mut` can select only the archetypes that carry all of
+    /// them.
+    fn mask(world: &World) -> u64;
+
+    /// Fetches the components for `entity`.
+    /// Only call this for an entity whose archetype mask is a superset of
+    /// `Self::mask`.
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item;
+}
+
+impl<'a, A: Any> JoinMut<'a> for (&'a mut A,) {
+    type Item = ComponentRefMut<'a, A>;
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        world.mark_changed(entity);
+        world.component_cell::<A>(entity)
+            .expect("JoinMut::fetch called on a non-matching entity")
+            .borrow_mut()
+    }
+}
+
+impl<'a, A: Any> JoinMut<'a> for (&'a A,) {
+    type Item = ComponentRef<'a, A>;
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        world.component_cell::<A>(entity)
+            .expect("JoinMut::fetch called on a non-matching entity")
+            .borrow()
+    }
+}
+
+impl<'a, A: Any, B: Any> JoinMut<'a> for (&'a mut A, &'a mut B) {
+    type Item = (ComponentRefMut<'a, A>, ComponentRefMut<'a, B>);
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>() | world.component_bit::<B>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        world.mark_changed(entity);
+        (
+            world.component_cell::<A>(entity)
+                .expect("JoinMut::fetch called on a non-matching entity").borrow_mut(),
+            world.component_cell::<B>(entity)
+                .expect("JoinMut::fetch called on a non-matching entity").borrow_mut(),
+        )
+    }
+}
+
+impl<'a, A: Any, B: Any> JoinMut<'a> for (&'a mut A, &'a B) {
+    type Item = (ComponentRefMut<'a, A>, ComponentRef<'a, B>);
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>() | world.component_bit::<B>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        world.mark_changed(entity);
+        (
+            world.component_cell::<A>(entity)
+                .expect("JoinMut::fetch called on a non-matching entity").borrow_mut(),
+            world.component_cell::<B>(entity)
+                .expect("JoinMut::fetch called on a non-matching entity").borrow(),
+        )
+    }
+}
+
+impl<'a, A: Any, B: Any> JoinMut<'a> for (&'a A, &'a mut B) {
+    type Item = (ComponentRef<'a, A>, ComponentRefMut<'a, B>);
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>() | world.component_bit::<B>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        world.mark_changed(entity);
+        (
+            world.component_cell::<A>(entity)
+                .expect("JoinMut::fetch called on a non-matching entity").borrow(),
+            world.component_cell::<B>(entity)
+                .expect("JoinMut::fetch called on a non-matching entity").borrow_mut(),
+        )
+    }
+}
+
+impl<'a, A: Any, B: Any> JoinMut<'a> for (&'a A, &'a B) {
+    type Item = (ComponentRef<'a, A>, ComponentRef<'a, B>);
+
+    fn mask(world: &World) -> u64 {
+        world.component_bit::<A>() | world.component_bit::<B>()
+    }
+
+    fn fetch(world: &'a World, entity: &Entity) -> Self::Item {
+        (
+            world.component_cell::<A>(entity)
+                .expect("JoinMut::fetch called on a non-matching entity").borrow(),
+            world.component_cell::<B>(entity)
+                .expect("JoinMut::fetch called on a non-matching entity").borrow(),
+        )
+    }
+}
+
+/// Iterates over the entities matching a `JoinMut` tuple `J`, together with
+/// (possibly mutable) references to those components.
+///
+/// Only visits archetypes whose mask is a superset of `J::mask`.
+pub struct QueryMutIter<'a, J> {
+    world: &'a World,
+    archetypes: Vec<&'a Archetype>,
+    arch_idx: usize,
+    row: usize,
+    _join: ::std::marker::PhantomData<J>,
+}
+
+impl<'a, J: JoinMut<'a>> Iterator for QueryMutIter<'a, J> {
+    type Item = (Entity, J::Item);
+
+    fn next(&mut self) -> Option<(Entity, J::Item)> {
+        loop {
+            let arch = match self.archetypes.get(self.arch_idx) {
+                Some(&arch) => arch,
+                None => return None,
+            };
+            if self.row >= arch.entities.len() {
+                self.arch_idx += 1;
+                self.row = 0;
+                continue;
+            }
+            let entity = arch.entities[self.row];
+            self.row += 1;
+            if self.world.ent_added.contains_key(&entity.idx) {
+                continue;
+            }
+            return Some((entity, J::fetch(self.world, &entity)));
+        }
+    }
+}
+
+/// A bitmask of component types. Built once with `with::<T>()` and then
+/// cheaply tested against an entity's archetype mask, so a query can skip
+/// non-matching archetypes entirely.
+///
+/// Mirrors stevenarella's bitset `Filter`: each registered component type
+/// gets a stable bit index (see `World::component_bit`), and a `Filter` is
+/// just the OR of the bits it requires.
+#[derive(Clone, Copy, Default)]
+pub struct Filter {
+    mask: u64,
+}
+
+impl Filter {
+    /// Returns an empty filter, matching every entity.
+    pub fn new() -> Filter {
+        Filter { mask: 0 }
+    }
+
+    /// Adds component type `T` to the set of components this filter requires.
+    pub fn with<T: Any>(mut self, world: &World) -> Filter {
+        self.mask |= world.component_bit::<T>();
+        self
+    }
+}
+
+/// Iterates over the entities whose archetype mask satisfies a `Filter`.
+///
+/// Only visits archetypes whose mask is a superset of the filter's mask.
+pub struct FilterIter<'a> {
+    world: &'a World,
+    archetypes: Vec<&'a Archetype>,
+    arch_idx: usize,
+    row: usize,
+}
+
+impl<'a> Iterator for FilterIter<'a> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Entity> {
+        loop {
+            let arch = match self.archetypes.get(self.arch_idx) {
+                Some(&arch) => arch,
+                None => return None,
+            };
+            if self.row >= arch.entities.len() {
+                self.arch_idx += 1;
+                self.row = 0;
+                continue;
+            }
+            let entity = arch.entities[self.row];
+            self.row += 1;
+            if self.world.ent_added.contains_key(&entity.idx) {
+                continue;
+            }
+            return Some(entity);
+        }
+    }
+}
+
+/// A component lifecycle hook: invoked with a restricted `DeferredWorld` and
+/// the `Entity` whose component triggered it.
+type Hook = Box<Fn(&mut DeferredWorld, Entity)>;
+
+/// Restricted view of the `World` passed to component lifecycle hooks
+/// (`on_add` / `on_insert` / `on_remove`).
+///
+/// Allows component reads and writes, same as `World`, but leaves out
+/// `add_entity`/`remove_entity`: running those reentrantly while a hook
+/// fires could invalidate the very entity or component state the hook is
+/// reacting to. `queue_remove_entity` lets a hook still request a despawn;
+/// it is only applied once the hook dispatch that triggered it returns.
+pub struct DeferredWorld<'a> {
+    world: &'a mut World,
+    pending_removes: Vec<Entity>,
+}
+
+impl<'a> DeferredWorld<'a> {
+    /// Returns a reference to a component. See `World::get_component`.
+    pub fn get_component<T: Any>(&self, entity: &Entity) -> Option<ComponentRef<T>> {
+        self.world.get_component::<T>(entity)
+    }
+
+    /// Returns a mutable reference to a component. See `World::get_mut_component`.
+    pub fn get_mut_component<T: Any>(&mut self, entity: &Entity) -> Option<&mut T> {
+        self.world.get_mut_component::<T>(entity)
+    }
+
+    /// Returns whether an entity has a specific component. See `World::has_component`.
+    pub fn has_component<T: Any>(&self, entity: &Entity) -> bool {
+        self.world.has_component::<T>(entity)
+    }
+
+    /// Adds or overwrites a component. See `World::add_component`.
+    pub fn add_component<T: Any>(&mut self, entity: &Entity, component: T) -> Option<T> {
+        self.world.add_component(entity, component)
+    }
+
+    /// Removes a component. See `World::remove_component`.
+    pub fn remove_component<T: Any>(&mut self, entity: &Entity) -> Option<T> {
+        self.world.remove_component::<T>(entity)
+    }
+
+    /// Queues `entity` for removal once the hook dispatch that called this
+    /// returns, instead of despawning it immediately.
+    pub fn queue_remove_entity(&mut self, entity: Entity) {
+        self.pending_removes.push(entity);
+    }
+}
+
+/// Depth-first iterator over the descendants of an entity, as recorded via
+/// `World::set_parent`. Does not include the entity it was created from.
+pub struct DescendantsIter<'a> {
+    world: &'a World,
+    stack: Vec<Entity>,
+}
+
+impl<'a> Iterator for DescendantsIter<'a> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Entity> {
+        let entity = match self.stack.pop() {
+            Some(entity) => entity,
+            None => return None,
+        };
+        for child in self.world.children(&entity).into_iter().rev() {
+            self.stack.push(child);
+        }
+        Some(entity)
+    }
+}
+
+/// A group of entities that all share the exact same set of component
+/// types, with each component type stored in its own contiguous column
+/// (struct-of-arrays), as in pyro/legion.
+///
+/// `entities[row]` and every `Vec<ComponentCell<T>>` column in `columns` are
+/// kept the same length and in step with each other: row `n` always refers
+/// to the same entity across every column.
+struct Archetype {
+    mask: u64,
+    entities: Vec<Entity>,
+    columns: AnyMap,
+}
+
+impl Archetype {
+    fn new(mask: u64) -> Archetype {
+        Archetype {
+            mask: mask,
+            entities: Vec::new(),
+            columns: AnyMap::new(),
+        }
+    }
+}
+
+/// Where an entity currently lives: which archetype (identified by its
+/// component mask) and which row within it. Updated on every archetype
+/// move and on every swap-remove, so `Entity{idx, uuid}` stays a stable
+/// handle regardless of how its components are physically stored.
+#[derive(Clone, Copy)]
+struct ArchetypeLocation {
+    mask: u64,
+    row: usize,
+}
+
+/// Type-erased "move one row between archetypes" function for a single
+/// component type, registered the first time that type is used.
+type ColumnMover = Box<Fn(&mut Archetype, &mut Archetype, usize)>;
+
+/// Type-erased "drop one row" function for a single component type, used
+/// when a despawned entity's row is cleared out of its archetype.
+type ColumnDropper = Box<Fn(&mut Archetype, usize)>;
+
+fn make_mover<T: Any>() -> ColumnMover {
+    Box::new(|src: &mut Archetype, dst: &mut Archetype, row: usize| {
+        let cell = src.columns.get_mut::<Vec<ComponentCell<T>>>()
+            .expect("column missing during archetype move")
+            .swap_remove(row);
+        if !dst.columns.contains::<Vec<ComponentCell<T>>>() {
+            dst.columns.insert(Vec::<ComponentCell<T>>::new());
+        }
+        dst.columns.get_mut::<Vec<ComponentCell<T>>>().unwrap().push(cell);
+    })
+}
+
+fn make_dropper<T: Any>() -> ColumnDropper {
+    Box::new(|arch: &mut Archetype, row: usize| {
+        arch.columns.get_mut::<Vec<ComponentCell<T>>>()
+            .expect("column missing while dropping a despawned entity's row")
+            .swap_remove(row);
+    })
+}
 
 /// Keeps track of entities and their components.
+///
+/// Components are stored in archetypes: every entity with the exact same
+/// set of component types lives in the same `Archetype`, which stores each
+/// component type as its own contiguous `Vec<ComponentCell<T>>` column.
+/// `add_component`/`remove_component` move an entity's row to the
+/// archetype for its new type set, reusing a cached transition edge (as in
+/// Bevy) when that exact transition has already been taken before.
 pub struct World {
     next_idx: usize,
     next_uuid: usize,
@@ -13,30 +573,80 @@ pub struct World {
     /// A uuid of `0` means an inactive entity.
     active: Vec<usize>,
     reusable_idxs: Vec<usize>,
-    /// List of all the components.
-    components: Vec<AnyMap>,
+
+    /// Archetypes, keyed by their exact component-type bitmask.
+    archetypes: HashMap<u64, Archetype>,
+    /// entity idx -> (archetype mask, row within that archetype).
+    locations: Vec<ArchetypeLocation>,
+
+    /// Assigns each registered component type a stable bit index, used by
+    /// archetype masks and `Filter`.
+    component_bits: RefCell<HashMap<TypeId, u32>>,
+    /// Reverse of `component_bits`, used to find which component types an
+    /// archetype mask refers to.
+    bit_types: RefCell<HashMap<u32, TypeId>>,
+    next_component_bit: Cell<u32>,
+    /// Cached archetype transitions, keyed by the (source mask, changed
+    /// type, is-add) triple, as in Bevy's archetype edges: once a type has
+    /// been added to (or removed from) a given archetype before, later
+    /// entities making the same move reuse the cached destination mask
+    /// instead of recomputing it.
+    archetype_edges: RefCell<HashMap<(u64, TypeId, bool), u64>>,
+    /// Type-erased "move one row between archetypes" functions, one per
+    /// component type, registered the first time that type is used.
+    column_movers: RefCell<HashMap<TypeId, ColumnMover>>,
+    /// Type-erased "drop one row" functions, used when despawning an entity.
+    column_droppers: RefCell<HashMap<TypeId, ColumnDropper>>,
+
+    /// Lifecycle hooks, keyed by the `TypeId` of the component they watch.
+    /// Each type's hook list is wrapped in its own `Rc<RefCell<..>>` so a
+    /// dispatch can clone the `Rc` out and iterate it without removing the
+    /// entry from the map: a hook that reentrantly triggers another dispatch
+    /// for the same type (e.g. on a different entity) still finds the hook
+    /// list in place, instead of racing the outer dispatch for ownership.
+    hooks_add: RefCell<HashMap<TypeId, Rc<RefCell<Vec<Hook>>>>>,
+    hooks_insert: RefCell<HashMap<TypeId, Rc<RefCell<Vec<Hook>>>>>,
+    hooks_remove: RefCell<HashMap<TypeId, Rc<RefCell<Vec<Hook>>>>>,
 
     parents: HashMap<Entity, Entity>,
 
     ent_added: HashMap<usize, usize>,
     ent_remove: HashMap<usize, usize>,
-    ent_changed: HashMap<usize, usize>,
+    /// Wrapped in a `RefCell` so that `query_mut` can mark entities as
+    /// changed while only holding `&World`.
+    ent_changed: RefCell<HashMap<usize, usize>>,
 }
 
 impl World {
     pub fn new() -> World {
+        let mut archetypes = HashMap::new();
+        archetypes.insert(0, Archetype::new(0));
+
         World {
             next_idx: 0,
             next_uuid: 1,
             active: Vec::new(),
             reusable_idxs: Vec::new(),
-            components: Vec::new(),
+
+            archetypes: archetypes,
+            locations: Vec::new(),
+
+            component_bits: RefCell::new(HashMap::new()),
+            bit_types: RefCell::new(HashMap::new()),
+            next_component_bit: Cell::new(0),
+            archetype_edges: RefCell::new(HashMap::new()),
+            column_movers: RefCell::new(HashMap::new()),
+            column_droppers: RefCell::new(HashMap::new()),
+
+            hooks_add: RefCell::new(HashMap::new()),
+            hooks_insert: RefCell::new(HashMap::new()),
+            hooks_remove: RefCell::new(HashMap::new()),
 
             parents: HashMap::new(),
 
             ent_added: HashMap::new(),
             ent_remove: HashMap::new(),
-            ent_changed: HashMap::new(),
+            ent_changed: RefCell::new(HashMap::new()),
         }
     }
 
@@ -52,8 +662,8 @@ impl World {
                 // No reusable idxs, so make a new one.
                 let idx = self.next_idx;
                 self.next_idx += 1;
-                // And add a new entry to the components vector.
-                self.components.push(AnyMap::new());
+                // And add a new (placeholder) entry to the location table.
+                self.locations.push(ArchetypeLocation { mask: 0, row: 0 });
                 idx
             },
             Some(idx) => idx,
@@ -72,7 +682,15 @@ impl World {
         // Register the entity as newly added.
         self.ent_added.insert(idx, uuid);
 
-        Entity { idx: idx, uuid: uuid }
+        let entity = Entity { idx: idx, uuid: uuid };
+
+        // Every new entity starts out in the empty archetype.
+        let empty = self.archetypes.entry(0).or_insert_with(|| Archetype::new(0));
+        let row = empty.entities.len();
+        empty.entities.push(entity);
+        self.locations[idx] = ArchetypeLocation { mask: 0, row: row };
+
+        entity
     }
 
     /// Slates an entity for removal.
@@ -86,26 +704,61 @@ impl World {
     /// checks all entries in the `parents` hashmap for validity.
     pub fn confirm_changes(&mut self) {
         // Remove all entities in the `remove` list.
-        for (&idx, &uuid) in self.ent_remove.iter() {
-            if self.is_valid_entity(&Entity{ idx: idx, uuid: uuid }) {
+        // Drained first so that running `on_remove` hooks below doesn't
+        // conflict with the borrow of `self.ent_remove`. Despawns that those
+        // hooks queue are drained into the same list and carried out before
+        // this call returns.
+        let mut removals: Vec<(usize, usize)> = self.ent_remove.drain().collect();
+        while let Some((idx, uuid)) = removals.pop() {
+            let entity = Entity{ idx: idx, uuid: uuid };
+            if self.is_valid_entity(&entity) {
+                // Run `on_remove` for every component type this entity still
+                // carries, before its row is actually cleared out.
+                let mask = self.locations[idx].mask;
+                let types: Vec<TypeId> = self.bit_types.borrow().iter()
+                    .filter(|&(&bit, _)| mask & (1u64 << bit) != 0)
+                    .map(|(_, &type_id)| type_id)
+                    .collect();
+                for type_id in types {
+                    // A hook dispatched earlier in this same loop may have
+                    // reentrantly removed this component already (e.g. its
+                    // `on_remove` called `remove_component` for another type
+                    // still in this snapshot). Recheck the live mask instead
+                    // of trusting the snapshot, so the hook doesn't fire
+                    // twice for the same removal.
+                    let still_present = self.component_bits.borrow().get(&type_id)
+                        .map_or(false, |&bit| self.locations[idx].mask & (1u64 << bit) != 0);
+                    if still_present {
+                        self.dispatch_remove(type_id, entity);
+                    }
+                }
+                removals.extend(self.ent_remove.drain());
+
                 self.active[idx] = 0;
                 self.reusable_idxs.push(idx);
-                // Clear the components associated with the entity.
-                self.components[idx].clear();
+                // Drop the entity's row out of its archetype.
+                self.remove_from_archetype(&entity);
             }
         }
 
         // Check if there are any invalid parent links.
         // This could probably be done quicker (that `clone()` doesn't look efficient).
         for (child, parent) in self.parents.clone().iter() {
-            if !(self.is_valid_entity(child) && self.is_valid_entity(parent)) {
+            let child_valid = self.is_valid_entity(child);
+            let parent_valid = self.is_valid_entity(parent);
+            if !(child_valid && parent_valid) {
+                if parent_valid && !child_valid {
+                    // The child was despawned: prune it from the surviving
+                    // parent's `Children` list too, not just `self.parents`.
+                    self.remove_child(parent, child);
+                }
                 self.parents.remove(child);
             }
         }
 
         self.ent_added.clear();
         self.ent_remove.clear();
-        self.ent_changed.clear();
+        self.ent_changed.borrow_mut().clear();
     }
 
     /// Checks if an `Entity` reference is valid.
@@ -127,14 +780,275 @@ impl World {
         }
     }
 
+    /// Records `entity` as changed. Used by `query_mut` to mark entities
+    /// whose mutable guard was taken, without needing `&mut self`.
+    fn mark_changed(&self, entity: &Entity) {
+        self.ent_changed.borrow_mut().insert(entity.idx, entity.uuid);
+    }
+
+    /// Returns the stable bitmask bit for component type `T`, registering it
+    /// the first time it is seen. Used to key archetypes and to build a
+    /// `Filter`.
+    fn component_bit<T: Any>(&self) -> u64 {
+        let type_id = TypeId::of::<T>();
+        if let Some(&bit) = self.component_bits.borrow().get(&type_id) {
+            return 1u64 << bit;
+        }
+        let bit = self.next_component_bit.get();
+        assert!(bit < 64, "more than 64 distinct component types are registered");
+        self.next_component_bit.set(bit + 1);
+        self.component_bits.borrow_mut().insert(type_id, bit);
+        self.bit_types.borrow_mut().insert(bit, type_id);
+        1u64 << bit
+    }
+
+    /// Registers the type-erased move/drop functions for `T`, the first
+    /// time `T` is used in `add_component`. Every component type that ends
+    /// up part of some archetype mask always goes through here first, so a
+    /// mover/dropper is guaranteed to exist for every bit an archetype mask
+    /// can have set.
+    fn register_column<T: Any>(&self) {
+        let type_id = TypeId::of::<T>();
+        if !self.column_movers.borrow().contains_key(&type_id) {
+            self.column_movers.borrow_mut().insert(type_id, make_mover::<T>());
+            self.column_droppers.borrow_mut().insert(type_id, make_dropper::<T>());
+        }
+    }
+
+    /// Returns the destination archetype mask for adding or removing the
+    /// component type `type_id` (with bitmask `bit`) from an entity
+    /// currently in the archetype `from_mask`, reusing the cached edge if
+    /// this exact transition has been taken before.
+    fn edge(&self, from_mask: u64, type_id: TypeId, bit: u64, is_add: bool) -> u64 {
+        let key = (from_mask, type_id, is_add);
+        if let Some(&to_mask) = self.archetype_edges.borrow().get(&key) {
+            return to_mask;
+        }
+        let to_mask = if is_add { from_mask | bit } else { from_mask & !bit };
+        self.archetype_edges.borrow_mut().insert(key, to_mask);
+        to_mask
+    }
+
+    /// Returns the `ComponentCell` backing entity's component `T`, if it has
+    /// one, by looking up its archetype and row. Used by `Join`/`JoinMut`
+    /// and the other per-type accessors.
+    fn component_cell<T: Any>(&self, entity: &Entity) -> Option<&ComponentCell<T>> {
+        let loc = self.locations[entity.idx];
+        self.archetypes.get(&loc.mask)
+            .and_then(|arch| arch.columns.get::<Vec<ComponentCell<T>>>())
+            .map(|column| &column[loc.row])
+    }
+
+    /// Moves `entity`'s row from its current archetype to the archetype
+    /// keyed by `new_mask`, migrating every component column common to
+    /// both (i.e. every bit set in both the old and the new mask), creating
+    /// the destination archetype if this is the first entity to reach it.
+    /// Returns the row the entity ends up at in the destination archetype.
+    ///
+    /// Leaves any column whose bit is only set in the old mask untouched
+    /// for the caller to deal with first (`remove_component` extracts that
+    /// value before calling this).
+    fn relocate(&mut self, entity: &Entity, new_mask: u64) -> usize {
+        let old_mask = self.locations[entity.idx].mask;
+        let old_row = self.locations[entity.idx].row;
+
+        let mut src = self.archetypes.remove(&old_mask).expect("entity's archetype is missing");
+        let mut dst = self.archetypes.remove(&new_mask).unwrap_or_else(|| Archetype::new(new_mask));
+
+        let keep_mask = old_mask & new_mask;
+        let type_ids: Vec<TypeId> = self.bit_types.borrow().iter()
+            .filter(|&(&bit, _)| keep_mask & (1u64 << bit) != 0)
+            .map(|(_, &type_id)| type_id)
+            .collect();
+
+        {
+            let movers = self.column_movers.borrow();
+            for type_id in &type_ids {
+                let mover = movers.get(type_id).expect("missing column mover for a registered component type");
+                mover(&mut src, &mut dst, old_row);
+            }
+        }
+
+        src.entities.swap_remove(old_row);
+        dst.entities.push(*entity);
+        let new_row = dst.entities.len() - 1;
+
+        // Fix up whichever entity got swapped into the vacated row.
+        if old_row < src.entities.len() {
+            let moved_entity = src.entities[old_row];
+            self.locations[moved_entity.idx].row = old_row;
+        }
+
+        self.locations[entity.idx] = ArchetypeLocation { mask: new_mask, row: new_row };
+
+        self.archetypes.insert(old_mask, src);
+        self.archetypes.insert(new_mask, dst);
+
+        new_row
+    }
+
+    /// Drops a despawned entity's row out of its archetype entirely,
+    /// keeping the location table consistent for whichever entity the
+    /// swap-remove brings into its place.
+    fn remove_from_archetype(&mut self, entity: &Entity) {
+        let loc = self.locations[entity.idx];
+        let mut arch = match self.archetypes.remove(&loc.mask) {
+            Some(arch) => arch,
+            None => return,
+        };
+
+        let type_ids: Vec<TypeId> = self.bit_types.borrow().iter()
+            .filter(|&(&bit, _)| loc.mask & (1u64 << bit) != 0)
+            .map(|(_, &type_id)| type_id)
+            .collect();
+
+        {
+            let droppers = self.column_droppers.borrow();
+            for type_id in &type_ids {
+                if let Some(dropper) = droppers.get(type_id) {
+                    dropper(&mut arch, loc.row);
+                }
+            }
+        }
+
+        arch.entities.swap_remove(loc.row);
+        if loc.row < arch.entities.len() {
+            let moved_entity = arch.entities[loc.row];
+            self.locations[moved_entity.idx].row = loc.row;
+        }
+
+        self.archetypes.insert(loc.mask, arch);
+    }
+
+    /// Registers `hook` to run the first time component `T` is added to an
+    /// entity (i.e. `add_component` returned `None`).
+    pub fn on_add<T: Any, F: Fn(&mut DeferredWorld, Entity) + 'static>(&mut self, hook: F) {
+        let entry = self.hooks_add.borrow_mut().entry(TypeId::of::<T>())
+            .or_insert_with(|| Rc::new(RefCell::new(Vec::new()))).clone();
+        entry.borrow_mut().push(Box::new(hook));
+    }
+
+    /// Registers `hook` to run whenever component `T` is added to an entity
+    /// that already had one (i.e. `add_component` returned `Some(old)`).
+    pub fn on_insert<T: Any, F: Fn(&mut DeferredWorld, Entity) + 'static>(&mut self, hook: F) {
+        let entry = self.hooks_insert.borrow_mut().entry(TypeId::of::<T>())
+            .or_insert_with(|| Rc::new(RefCell::new(Vec::new()))).clone();
+        entry.borrow_mut().push(Box::new(hook));
+    }
+
+    /// Registers `hook` to run whenever component `T` is removed from an
+    /// entity, either through `remove_component` or because the entity was
+    /// despawned while still carrying the component.
+    pub fn on_remove<T: Any, F: Fn(&mut DeferredWorld, Entity) + 'static>(&mut self, hook: F) {
+        let entry = self.hooks_remove.borrow_mut().entry(TypeId::of::<T>())
+            .or_insert_with(|| Rc::new(RefCell::new(Vec::new()))).clone();
+        entry.borrow_mut().push(Box::new(hook));
+    }
+
+    /// Runs every `on_add` hook registered for `type_id`, via a
+    /// `DeferredWorld`, then applies any entity removals the hooks queued.
+    ///
+    /// Clones the `Rc` holding the hook list out of the map instead of
+    /// removing it, so a hook that reentrantly dispatches `type_id` again
+    /// (e.g. adding the same component to another entity) still finds and
+    /// runs the same hooks, rather than the list appearing empty.
+    fn dispatch_add(&mut self, type_id: TypeId, entity: Entity) {
+        let hooks = self.hooks_add.borrow().get(&type_id).cloned();
+        if let Some(hooks) = hooks {
+            let pending = {
+                let mut deferred = DeferredWorld { world: self, pending_removes: Vec::new() };
+                for hook in hooks.borrow().iter() {
+                    hook(&mut deferred, entity);
+                }
+                deferred.pending_removes
+            };
+            for queued in pending {
+                self.remove_entity(queued);
+            }
+        }
+    }
+
+    /// Runs every `on_insert` hook registered for `type_id`, via a
+    /// `DeferredWorld`, then applies any entity removals the hooks queued.
+    ///
+    /// See `dispatch_add` for why the hook list is cloned out rather than
+    /// removed.
+    fn dispatch_insert(&mut self, type_id: TypeId, entity: Entity) {
+        let hooks = self.hooks_insert.borrow().get(&type_id).cloned();
+        if let Some(hooks) = hooks {
+            let pending = {
+                let mut deferred = DeferredWorld { world: self, pending_removes: Vec::new() };
+                for hook in hooks.borrow().iter() {
+                    hook(&mut deferred, entity);
+                }
+                deferred.pending_removes
+            };
+            for queued in pending {
+                self.remove_entity(queued);
+            }
+        }
+    }
+
+    /// Runs every `on_remove` hook registered for `type_id`, via a
+    /// `DeferredWorld`, then applies any entity removals the hooks queued.
+    ///
+    /// See `dispatch_add` for why the hook list is cloned out rather than
+    /// removed.
+    fn dispatch_remove(&mut self, type_id: TypeId, entity: Entity) {
+        let hooks = self.hooks_remove.borrow().get(&type_id).cloned();
+        if let Some(hooks) = hooks {
+            let pending = {
+                let mut deferred = DeferredWorld { world: self, pending_removes: Vec::new() };
+                for hook in hooks.borrow().iter() {
+                    hook(&mut deferred, entity);
+                }
+                deferred.pending_removes
+            };
+            for queued in pending {
+                self.remove_entity(queued);
+            }
+        }
+    }
+
     /// Adds a new component to an entity.
     /// If the entity already had that component, that component is returned.
     /// Otherwise, `None` is returned.
     pub fn add_component<T: Any>(&mut self, entity: &Entity, component: T) -> Option<T> {
-        if self.is_valid_entity(entity) {
-            self.ent_changed.insert(entity.idx, entity.uuid);
-            return self.components[entity.idx].insert(component);
+        if !self.is_valid_entity(entity) {
+            return None;
+        }
+        self.mark_changed(entity);
+        self.register_column::<T>();
+        let bit = self.component_bit::<T>();
+        let old_mask = self.locations[entity.idx].mask;
+
+        if old_mask & bit != 0 {
+            // Already has the component: overwrite it in place, no
+            // archetype move needed.
+            let old_row = self.locations[entity.idx].row;
+            let old_value = {
+                let arch = self.archetypes.get_mut(&old_mask).expect("entity's archetype is missing");
+                let column = arch.columns.get_mut::<Vec<ComponentCell<T>>>().expect("missing column for a set bit");
+                ::std::mem::replace(&mut column[old_row], ComponentCell::new(component)).into_inner()
+            };
+            self.dispatch_insert(TypeId::of::<T>(), *entity);
+            return Some(old_value);
+        }
+
+        let new_mask = self.edge(old_mask, TypeId::of::<T>(), bit, true);
+        let new_row = self.relocate(entity, new_mask);
+
+        {
+            let arch = self.archetypes.get_mut(&new_mask).expect("destination archetype is missing");
+            if !arch.columns.contains::<Vec<ComponentCell<T>>>() {
+                arch.columns.insert(Vec::<ComponentCell<T>>::new());
+            }
+            let column = arch.columns.get_mut::<Vec<ComponentCell<T>>>().unwrap();
+            debug_assert_eq!(column.len(), new_row);
+            column.push(ComponentCell::new(component));
         }
+
+        self.dispatch_add(TypeId::of::<T>(), *entity);
         None
     }
 
@@ -142,7 +1056,8 @@ impl World {
     /// Does also search the parents for the component.
     pub fn has_component<T: Any>(&self, entity: &Entity) -> bool {
         if self.is_valid_entity(entity) {
-            if self.components[entity.idx].contains::<T>() {
+            let bit = self.component_bit::<T>();
+            if self.locations[entity.idx].mask & bit != 0 {
                 return true;
             } else {
                 // This entity doesn't have the component.
@@ -151,7 +1066,7 @@ impl World {
                 println!("Start {}, {}", cur_ent.idx, cur_ent.uuid);
                 loop  {
                     if self.is_valid_entity(&cur_ent) {
-                        if self.components[cur_ent.idx].contains::<T>() {
+                        if self.locations[cur_ent.idx].mask & bit != 0 {
                             return true;
                         }
                         if let Some(parent) = self.get_parent(&cur_ent) {
@@ -174,19 +1089,19 @@ impl World {
     /// Returns a reference to a component.
     /// If a component does not exist, but it does in the parent,
     /// the parent's component will be returned.
-    pub fn get_component<T: Any>(&self, entity: &Entity) -> Option<&T> {
+    pub fn get_component<T: Any>(&self, entity: &Entity) -> Option<ComponentRef<T>> {
         if self.is_valid_entity(entity) {
             // See if the component is there, if so: return it.
-            match self.components[entity.idx].get::<T>() {
-                Some(comp) => return Some(comp),
+            match self.component_cell::<T>(entity) {
+                Some(comp) => return Some(comp.borrow()),
                 None => {
                     // This entity doesn't have the component.
                     // See if has inherited it from a parent.
                     let mut cur_ent = *entity;
                     loop  {
                         if self.is_valid_entity(&cur_ent) {
-                            if let Some(comp) = self.components[cur_ent.idx].get::<T>() {
-                                return Some(comp);
+                            if let Some(comp) = self.component_cell::<T>(&cur_ent) {
+                                return Some(comp.borrow());
                             }
                             if let Some(parent) = self.get_parent(&cur_ent) {
                                 cur_ent = parent;
@@ -210,14 +1125,12 @@ impl World {
     /// as this will easily lead to bugs that are very hard to debug.
     pub fn get_mut_component<T: Any>(&mut self, entity: &Entity) -> Option<&mut T> {
         if self.is_valid_entity(entity) {
-            let comp = self.components[entity.idx].get_mut::<T>();
-
-            return match comp {
-                Some(val) => {
-                    self.ent_changed.insert(entity.idx, entity.uuid);
-                    Some(val)
-                },
-                None => None,
+            let loc = self.locations[entity.idx];
+            if let Some(arch) = self.archetypes.get_mut(&loc.mask) {
+                if let Some(column) = arch.columns.get_mut::<Vec<ComponentCell<T>>>() {
+                    self.ent_changed.borrow_mut().insert(entity.idx, entity.uuid);
+                    return Some(column[loc.row].get_mut());
+                }
             }
         }
         None
@@ -226,28 +1139,48 @@ impl World {
     /// Removes a component from an entity.
     /// Returning the component if it existed, or `None` if it didn't.
     pub fn remove_component<T: Any>(&mut self, entity: &Entity) -> Option<T> {
-        if self.is_valid_entity(entity) {
-            let res = self.components[entity.idx].remove::<T>();
-
-            return match res {
-                Some(res) => {
-                    self.ent_changed.insert(entity.idx, entity.uuid);
-                    Some(res)
-                },
-                None => None,
-            }
+        if !self.is_valid_entity(entity) {
+            return None;
         }
-        None
+        let bit = self.component_bit::<T>();
+        let old_mask = self.locations[entity.idx].mask;
+        if old_mask & bit == 0 {
+            return None;
+        }
+        let old_row = self.locations[entity.idx].row;
+
+        let removed = {
+            let arch = self.archetypes.get_mut(&old_mask).expect("entity's archetype is missing");
+            let column = arch.columns.get_mut::<Vec<ComponentCell<T>>>().expect("missing column for a set bit");
+            column.swap_remove(old_row).into_inner()
+        };
+
+        let new_mask = self.edge(old_mask, TypeId::of::<T>(), bit, false);
+        self.relocate(entity, new_mask);
+
+        self.ent_changed.borrow_mut().insert(entity.idx, entity.uuid);
+        self.dispatch_remove(TypeId::of::<T>(), *entity);
+        Some(removed)
     }
 
     /// Sets the parent of an entity.
     /// Checks if both the entity and the parent are valid.
+    /// Keeps the parent's `Children` component in sync: the entity is added
+    /// to the new parent's list, and removed from the old parent's list if
+    /// it was already parented elsewhere.
     pub fn set_parent(&mut self, entity: &Entity, parent: &Entity) -> bool {
-        if self.is_valid_entity(entity) && self.is_valid_entity(parent) {
-            self.parents.insert(entity.clone(), parent.clone());
-        } else {
+        if !(self.is_valid_entity(entity) && self.is_valid_entity(parent)) {
             return false
         }
+
+        if let Some(old_parent) = self.parents.get(entity).cloned() {
+            if old_parent != *parent {
+                self.remove_child(&old_parent, entity);
+            }
+        }
+
+        self.parents.insert(entity.clone(), parent.clone());
+        self.add_child(parent, entity);
         true
     }
 
@@ -262,9 +1195,66 @@ impl World {
     }
 
     /// Temoves the parenting link from an Entity.
+    /// Also removes the entity from its old parent's `Children` component.
     pub fn unlink_parent(&mut self, entity: &Entity) {
         if self.is_valid_entity(entity) {
-            self.parents.remove(entity);
+            if let Some(parent) = self.parents.remove(entity) {
+                self.remove_child(&parent, entity);
+            }
+        }
+    }
+
+    /// Adds `child` to `parent`'s `Children` component, creating it if the
+    /// parent didn't have one yet.
+    fn add_child(&mut self, parent: &Entity, child: &Entity) {
+        if let Some(children) = self.get_mut_component::<Children>(parent) {
+            if !children.0.contains(child) {
+                children.0.push(*child);
+            }
+            return;
+        }
+        self.add_component(parent, Children(vec![*child]));
+    }
+
+    /// Removes `child` from `parent`'s `Children` component, if it has one.
+    fn remove_child(&mut self, parent: &Entity, child: &Entity) {
+        if let Some(children) = self.get_mut_component::<Children>(parent) {
+            children.0.retain(|c| c != child);
+        }
+    }
+
+    /// Returns the (direct) children of `entity`, as recorded by `set_parent`.
+    /// Empty if the entity is invalid or has no children.
+    pub fn children(&self, entity: &Entity) -> Vec<Entity> {
+        if self.is_valid_entity(entity) {
+            if let Some(cell) = self.component_cell::<Children>(entity) {
+                // Goes through the borrow-tracked guard and clones the list
+                // out, rather than returning a bare reference: a live
+                // `ComponentRefMut<Children>` held from `query_mut` would
+                // otherwise alias this read with no borrow-flag protection.
+                return cell.borrow().0.clone();
+            }
+        }
+        Vec::new()
+    }
+
+    /// Returns a depth-first iterator over every descendant of `entity`
+    /// (children, grandchildren, and so on), not including `entity` itself.
+    pub fn descendants(&self, entity: &Entity) -> DescendantsIter {
+        let mut stack = self.children(entity);
+        stack.reverse();
+        DescendantsIter {
+            world: self,
+            stack: stack,
+        }
+    }
+
+    /// Recursively applies `f` to `root` and then to every descendant of
+    /// `root`, depth-first, as in the legion/bevy transform hierarchy update.
+    pub fn run_on_hierarchy<F: FnMut(&World, Entity)>(&self, root: Entity, mut f: F) {
+        f(self, root);
+        for descendant in self.descendants(&root) {
+            f(self, descendant);
         }
     }
 
@@ -294,6 +1284,73 @@ impl World {
             .collect::<Vec<Entity>>()
     }
 
+    /// Returns a lazy iterator yielding only the entities that have every
+    /// component type in `J`, together with references to those components.
+    ///
+    /// ```ignore
+    /// for (entity, (pos, vel)) in world.query::<(Position, Velocity)>() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn query<'a, J: Join<'a>>(&'a self) -> QueryIter<'a, J> {
+        let required = J::mask(self);
+        let archetypes = self.archetypes.values()
+            .filter(|arch| arch.mask & required == required)
+            .collect();
+        QueryIter {
+            world: self,
+            archetypes: archetypes,
+            arch_idx: 0,
+            row: 0,
+            _join: ::std::marker::PhantomData,
+        }
+    }
+
+    /// Returns a lazy iterator yielding only the entities that have every
+    /// component type in `J`, handing out `&mut` references for the `&mut A`
+    /// members of the tuple and shared references for the `&A` members.
+    /// Several distinct component types can be borrowed mutably in the same
+    /// pass, since each is backed by its own borrow-tracked `ComponentCell`.
+    ///
+    /// ```ignore
+    /// for (entity, (pos, vel)) in world.query_mut::<(&mut Position, &Velocity)>() {
+    ///     pos.x += vel.x;
+    /// }
+    /// ```
+    pub fn query_mut<'a, J: JoinMut<'a>>(&'a self) -> QueryMutIter<'a, J> {
+        let required = J::mask(self);
+        let archetypes = self.archetypes.values()
+            .filter(|arch| arch.mask & required == required)
+            .collect();
+        QueryMutIter {
+            world: self,
+            archetypes: archetypes,
+            arch_idx: 0,
+            row: 0,
+            _join: ::std::marker::PhantomData,
+        }
+    }
+
+    /// Builds an empty `Filter` for this world. Add component types to it
+    /// with `Filter::with::<T>(world)`, then pass it to `World::filtered`.
+    pub fn filter(&self) -> Filter {
+        Filter::new()
+    }
+
+    /// Returns a lazy iterator yielding only the entities whose archetype
+    /// mask satisfies `filter`.
+    pub fn filtered<'a>(&'a self, filter: Filter) -> FilterIter<'a> {
+        let archetypes = self.archetypes.values()
+            .filter(|arch| arch.mask & filter.mask == filter.mask)
+            .collect();
+        FilterIter {
+            world: self,
+            archetypes: archetypes,
+            arch_idx: 0,
+            row: 0,
+        }
+    }
+
     /// Returns a vector listing all the recently added entities.
     pub fn list_additions(&self) -> Vec<Entity> {
         self.ent_added.iter()
@@ -310,7 +1367,7 @@ impl World {
     /// Returns a vector listing all the entities that have changed since the last call of.
     /// `confirm_changes`.
     pub fn list_changes(&self) -> Vec<Entity> {
-        self.ent_changed.iter()
+        self.ent_changed.borrow().iter()
             .map(|(&idx, &uuid)| Entity{ idx: idx, uuid: uuid })
             .collect::<Vec<Entity>>()
     }
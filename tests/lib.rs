@@ -1,5 +1,8 @@
 extern crate srecs;
 
+use std::cell::Cell;
+use std::rc::Rc;
+
 use srecs::Entity;
 use srecs::world::World;
 use srecs::components::*;
@@ -10,6 +13,13 @@ struct Position {
     y: u64,
 }
 
+/// Struct to be used as a second, independent component, for tests that
+/// need to query more than one component type at once.
+struct Velocity {
+    x: u64,
+    y: u64,
+}
+
 #[test]
 fn adding_and_deleting_entities() {
     let mut world = World::new();
@@ -238,6 +248,228 @@ fn adding_and_removing_components() {
     assert!(pos_count == 4);
 }
 
+#[test]
+fn query_test() {
+    let mut world = World::new();
+
+    let e1 = world.add_entity();
+    let e2 = world.add_entity();
+    let e3 = world.add_entity();
+
+    world.add_component(&e1, Position{ x: 1, y: 1 });
+    world.add_component(&e1, Velocity{ x: 2, y: 2 });
+
+    world.add_component(&e2, Position{ x: 3, y: 3 });
+
+    world.add_component(&e3, Velocity{ x: 4, y: 4 });
+
+    world.confirm_changes();
+
+    let mut found = Vec::new();
+    for (ent, (pos,)) in world.query::<(Position,)>() {
+        found.push((ent, pos.x, pos.y));
+    }
+
+    // Only e1 and e2 have a Position component.
+    assert!(found.len() == 2);
+    assert!(found.iter().any(|&(ent, x, y)| ent == e1 && x == 1 && y == 1));
+    assert!(found.iter().any(|&(ent, x, y)| ent == e2 && x == 3 && y == 3));
+
+    let mut both = 0;
+    for (ent, (pos, vel)) in world.query::<(Position, Velocity)>() {
+        assert!(ent == e1);
+        assert!(pos.x == 1 && vel.x == 2);
+        both += 1;
+    }
+    // Only e1 has both a Position and a Velocity component.
+    assert!(both == 1);
+}
+
+#[test]
+fn query_mut_test() {
+    let mut world = World::new();
+
+    let e1 = world.add_entity();
+    let e2 = world.add_entity();
+
+    world.add_component(&e1, Position{ x: 1, y: 1 });
+    world.add_component(&e1, Velocity{ x: 2, y: 3 });
+    world.add_component(&e2, Position{ x: 5, y: 5 });
+
+    world.confirm_changes();
+
+    // Mutate Position by Velocity for every entity that has both.
+    for (_, (mut pos, vel)) in world.query_mut::<(&mut Position, &Velocity)>() {
+        pos.x += vel.x;
+        pos.y += vel.y;
+    }
+
+    match world.get_component::<Position>(&e1) {
+        Some(pos) => {
+            assert!(pos.x == 3);
+            assert!(pos.y == 4);
+        },
+        None => panic!("Entity 1 should still have a Position component."),
+    }
+
+    // Entity 2 has no Velocity, so its Position should be untouched.
+    match world.get_component::<Position>(&e2) {
+        Some(pos) => {
+            assert!(pos.x == 5);
+            assert!(pos.y == 5);
+        },
+        None => panic!("Entity 2 should still have a Position component."),
+    };
+}
+
+#[test]
+fn filter_test() {
+    let mut world = World::new();
+
+    let e1 = world.add_entity();
+    let e2 = world.add_entity();
+    let e3 = world.add_entity();
+
+    world.add_component(&e1, Position{ x: 0, y: 0 });
+    world.add_component(&e1, Velocity{ x: 0, y: 0 });
+    world.add_component(&e2, Position{ x: 0, y: 0 });
+    world.add_component(&e3, Velocity{ x: 0, y: 0 });
+
+    world.confirm_changes();
+
+    let filter = world.filter().with::<Position>(&world).with::<Velocity>(&world);
+    let matched: Vec<Entity> = world.filtered(filter).collect();
+
+    // Only entity 1 has both a Position and a Velocity component.
+    assert!(matched.len() == 1);
+    assert!(matched[0] == e1);
+}
+
+#[test]
+fn hook_test() {
+    let mut world = World::new();
+
+    let add_count = Rc::new(Cell::new(0));
+    let remove_count = Rc::new(Cell::new(0));
+
+    {
+        let add_count = add_count.clone();
+        world.on_add::<Position, _>(move |_world, _entity| {
+            add_count.set(add_count.get() + 1);
+        });
+    }
+    {
+        let remove_count = remove_count.clone();
+        world.on_remove::<Position, _>(move |_world, _entity| {
+            remove_count.set(remove_count.get() + 1);
+        });
+    }
+
+    let e1 = world.add_entity();
+    world.confirm_changes();
+
+    world.add_component(&e1, Position{ x: 0, y: 0 });
+    assert!(add_count.get() == 1);
+
+    world.remove_component::<Position>(&e1);
+    assert!(remove_count.get() == 1);
+
+    // Despawning an entity that still carries the component should also
+    // fire `on_remove`.
+    world.add_component(&e1, Position{ x: 0, y: 0 });
+    assert!(add_count.get() == 2);
+
+    world.remove_entity(e1);
+    world.confirm_changes();
+    assert!(remove_count.get() == 2);
+}
+
+#[test]
+fn hierarchy_test() {
+    let mut world = World::new();
+
+    let root = world.add_entity();
+    let child_a = world.add_entity();
+    let child_b = world.add_entity();
+    let grand_child = world.add_entity();
+
+    world.confirm_changes();
+
+    world.set_parent(&child_a, &root);
+    world.set_parent(&child_b, &root);
+    world.set_parent(&grand_child, &child_a);
+
+    let children = world.children(&root);
+    assert!(children.len() == 2);
+    assert!(children.contains(&child_a));
+    assert!(children.contains(&child_b));
+
+    let descendants: Vec<Entity> = world.descendants(&root).collect();
+    assert!(descendants.len() == 3);
+    assert!(descendants.contains(&grand_child));
+
+    let mut visited = Vec::new();
+    world.run_on_hierarchy(root, |_world, entity| {
+        visited.push(entity);
+    });
+    // `run_on_hierarchy` visits `root` itself plus every descendant.
+    assert!(visited.len() == 4);
+    assert!(visited[0] == root);
+
+    // Despawning child_a should also drop it out of root's Children list.
+    world.remove_entity(child_a);
+    world.confirm_changes();
+
+    let children_after = world.children(&root);
+    assert!(children_after.len() == 1);
+    assert!(children_after.contains(&child_b));
+}
+
+#[test]
+fn archetype_move_test() {
+    let mut world = World::new();
+
+    let e1 = world.add_entity();
+    let e2 = world.add_entity();
+    let e3 = world.add_entity();
+
+    world.confirm_changes();
+
+    world.add_component(&e1, Position{ x: 1, y: 1 });
+    world.add_component(&e2, Position{ x: 2, y: 2 });
+    world.add_component(&e3, Position{ x: 3, y: 3 });
+
+    // Moves entity 2 to a different archetype (Position + Velocity).
+    world.add_component(&e2, Velocity{ x: 9, y: 9 });
+
+    // Entities 1 and 3 should be unaffected by 2's archetype move.
+    match world.get_component::<Position>(&e1) {
+        Some(pos) => assert!(pos.x == 1 && pos.y == 1),
+        None => panic!("Entity 1 should still have its Position component."),
+    }
+    match world.get_component::<Position>(&e3) {
+        Some(pos) => assert!(pos.x == 3 && pos.y == 3),
+        None => panic!("Entity 3 should still have its Position component."),
+    }
+
+    // Moves entity 2 back to the Position-only archetype.
+    world.remove_component::<Velocity>(&e2);
+    match world.get_component::<Position>(&e2) {
+        Some(pos) => assert!(pos.x == 2 && pos.y == 2),
+        None => panic!("Entity 2 should still have its Position component after losing Velocity."),
+    }
+
+    // Despawning entity 1 swap-removes its row; entity 3's row must not be
+    // disturbed by that swap.
+    world.remove_entity(e1);
+    world.confirm_changes();
+
+    match world.get_component::<Position>(&e3) {
+        Some(pos) => assert!(pos.x == 3 && pos.y == 3),
+        None => panic!("Entity 3 should still have its Position component after a sibling despawned."),
+    };
+}
+
 #[test]
 fn parenting_test() {
     let mut world = World::new();
@@ -249,22 +481,26 @@ fn parenting_test() {
     world.confirm_changes();
 
     world.add_component(&parent, Position{ x: 10, y: 12 });
-    world.add_component(&child, Parent(parent));
-    world.add_component(&grand_child, Parent(child));
+    world.set_parent(&child, &parent);
+    world.set_parent(&grand_child, &child);
 
     // Check if the parent still has the Position component.
-    if let Some(pos) = world.get_component::<Position>(&parent) {
-        assert!(pos.x == 10);
-        assert!(pos.y == 12);
-    } else {
-        panic!("The parent should still have it's position component, but it doesn't.");
+    let parent_pos = world.get_component::<Position>(&parent);
+    match parent_pos {
+        Some(pos) => {
+            assert!(pos.x == 10);
+            assert!(pos.y == 12);
+        },
+        None => panic!("The parent should still have it's position component, but it doesn't."),
     }
 
     // Check if the grandchild has indeed inherited the Position from it's grandparent.
-    if let Some(pos) = world.get_component::<Position>(&grand_child) {
-        assert!(pos.x == 10);
-        assert!(pos.y == 12);
-    } else {
-        panic!("The grandchild should have inherited the Position component, it didn't.");
+    let grand_child_pos = world.get_component::<Position>(&grand_child);
+    match grand_child_pos {
+        Some(pos) => {
+            assert!(pos.x == 10);
+            assert!(pos.y == 12);
+        },
+        None => panic!("The grandchild should have inherited the Position component, it didn't."),
     }
 }